@@ -0,0 +1,146 @@
+use serde::Deserialize;
+use std::collections::HashSet;
+use std::fs;
+
+const DEFAULT_POLL_INTERVAL_MS: u64 = 1000;
+const DEFAULT_JOURNAL_MODE: &str = "WAL";
+
+/// Controls whether an `update` event carries only the fields that changed or the full
+/// before/after row. Defaults to `DiffOnly` to match the tool's pre-config behavior,
+/// where every update was reported as a diff; `FullRow` is opt-in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureMode {
+    #[default]
+    DiffOnly,
+    FullRow,
+}
+
+/// Layered configuration for the monitored database, mirroring current CLI defaults when
+/// no config file is supplied.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub poll_interval_ms: u64,
+    pub include_tables: Option<HashSet<String>>,
+    pub exclude_tables: Option<HashSet<String>>,
+    pub capture_mode: CaptureMode,
+    pub journal_mode: Option<String>,
+    /// Path of an NDJSON file to append delivered batches to. Unset disables the sink.
+    pub ndjson_sink_path: Option<String>,
+    /// URL to POST delivered batches to as a JSON array. Unset disables the sink.
+    pub webhook_sink_url: Option<String>,
+    /// Print Fivetran-style upsert/soft-delete records instead of raw `ChangeEvent` JSON.
+    pub replication_mode: bool,
+    /// In `replication_mode`, emit both the pre- and post-image of an update instead of
+    /// just the post-image.
+    pub history_mode: bool,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            poll_interval_ms: DEFAULT_POLL_INTERVAL_MS,
+            include_tables: None,
+            exclude_tables: None,
+            capture_mode: CaptureMode::DiffOnly,
+            journal_mode: None,
+            ndjson_sink_path: None,
+            webhook_sink_url: None,
+            replication_mode: false,
+            history_mode: false,
+        }
+    }
+}
+
+impl Settings {
+    /// Loads settings from `path` if given, otherwise returns CLI-compatible defaults.
+    pub fn load(path: Option<&str>) -> Settings {
+        let path = match path {
+            Some(path) => path,
+            None => return Settings::default(),
+        };
+        let contents = fs::read_to_string(path).unwrap_or_else(|err| {
+            eprintln!("Could not read config file {}: {}", path, err);
+            std::process::exit(1);
+        });
+        let settings: Settings = toml::from_str(&contents).unwrap_or_else(|err| {
+            eprintln!("Invalid config file {}: {}", path, err);
+            std::process::exit(1);
+        });
+        if let Err(err) = settings.validate() {
+            eprintln!("Invalid config file {}: {}", path, err);
+            std::process::exit(1);
+        }
+        settings
+    }
+
+    /// Checks cross-field invariants that `#[derive(Deserialize)]` can't express.
+    pub fn validate(&self) -> std::result::Result<(), String> {
+        if self.replication_mode && self.capture_mode == CaptureMode::DiffOnly {
+            return Err(
+                "replication_mode requires capture_mode = \"full_row\"; diff_only discards \
+                 the non-PK column data replication needs to build upsert/history records"
+                    .to_string(),
+            );
+        }
+        Ok(())
+    }
+
+    pub fn journal_mode(&self) -> &str {
+        self.journal_mode.as_deref().unwrap_or(DEFAULT_JOURNAL_MODE)
+    }
+
+    /// Applies the include/exclude allow-deny list to a set of discovered table names.
+    pub fn filter_tables(&self, tables: Vec<String>) -> Vec<String> {
+        tables
+            .into_iter()
+            .filter(|table| {
+                let included = self
+                    .include_tables
+                    .as_ref()
+                    .is_none_or(|set| set.contains(table));
+                let excluded = self
+                    .exclude_tables
+                    .as_ref()
+                    .is_some_and(|set| set.contains(table));
+                included && !excluded
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replication_mode_rejects_diff_only_capture() {
+        let settings = Settings {
+            replication_mode: true,
+            capture_mode: CaptureMode::DiffOnly,
+            ..Settings::default()
+        };
+        assert!(settings.validate().is_err());
+    }
+
+    #[test]
+    fn replication_mode_allows_full_row_capture() {
+        let settings = Settings {
+            replication_mode: true,
+            capture_mode: CaptureMode::FullRow,
+            ..Settings::default()
+        };
+        assert!(settings.validate().is_ok());
+    }
+
+    #[test]
+    fn non_replication_settings_allow_diff_only_capture() {
+        let settings = Settings {
+            replication_mode: false,
+            capture_mode: CaptureMode::DiffOnly,
+            ..Settings::default()
+        };
+        assert!(settings.validate().is_ok());
+    }
+}
@@ -0,0 +1,302 @@
+use rusqlite::{params, Connection, Result};
+use serde_json::{json, Value};
+
+pub fn create_log_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS change_stream_log (
+            id         INTEGER PRIMARY KEY AUTOINCREMENT,
+            table_name TEXT NOT NULL,
+            action     TEXT NOT NULL,
+            rowid      INTEGER,
+            new_data   TEXT,
+            old_data   TEXT,
+            ts         DATETIME DEFAULT CURRENT_TIMESTAMP
+        );
+        "#,
+    )?;
+    Ok(())
+}
+
+/// Tracks each consumer's last-processed `change_stream_log.id` so a restart resumes
+/// instead of replaying the whole history.
+pub fn create_cursor_table(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        r#"
+        CREATE TABLE IF NOT EXISTS change_stream_cursor (
+            consumer TEXT PRIMARY KEY,
+            last_id  INTEGER NOT NULL
+        );
+        "#,
+    )?;
+    Ok(())
+}
+
+pub fn load_cursor(conn: &Connection, consumer: &str) -> Result<i64> {
+    conn.query_row(
+        "SELECT last_id FROM change_stream_cursor WHERE consumer = ?1;",
+        params![consumer],
+        |row| row.get(0),
+    )
+    .or_else(|err| match err {
+        rusqlite::Error::QueryReturnedNoRows => Ok(0),
+        other => Err(other),
+    })
+}
+
+pub fn save_cursor(conn: &Connection, consumer: &str, last_id: i64) -> Result<()> {
+    conn.execute(
+        "INSERT INTO change_stream_cursor (consumer, last_id) VALUES (?1, ?2)
+         ON CONFLICT(consumer) DO UPDATE SET last_id = excluded.last_id;",
+        params![consumer, last_id],
+    )?;
+    Ok(())
+}
+
+/// Deletes log rows that every registered consumer has already moved past. Returns the
+/// number of rows removed.
+pub fn prune_log(conn: &Connection) -> Result<usize> {
+    let min_last_id: Option<i64> = conn.query_row(
+        "SELECT MIN(last_id) FROM change_stream_cursor;",
+        [],
+        |row| row.get(0),
+    )?;
+    match min_last_id {
+        Some(min_last_id) => conn.execute(
+            "DELETE FROM change_stream_log WHERE id <= ?1;",
+            params![min_last_id],
+        ),
+        None => Ok(0),
+    }
+}
+
+pub fn get_user_tables(conn: &Connection) -> Result<Vec<String>> {
+    let mut stmt = conn.prepare(
+        "SELECT name FROM sqlite_master
+         WHERE type='table'
+           AND name NOT LIKE 'sqlite_%'
+           AND name NOT IN ('change_stream_log', 'change_stream_cursor');",
+    )?;
+    let tables_iter = stmt.query_map([], |row| row.get(0))?;
+    let mut tables = Vec::new();
+    for table in tables_iter {
+        tables.push(table?);
+    }
+    Ok(tables)
+}
+
+pub fn get_json_exprs(conn: &Connection, table: &str) -> Result<(String, String)> {
+    let query = format!("PRAGMA table_info({})", table);
+    let mut stmt = conn.prepare(&query)?;
+    let mut new_expr_parts = Vec::new();
+    let mut old_expr_parts = Vec::new();
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        let col: String = row.get(1)?;
+        new_expr_parts.push(format!("'{}', NEW.{}", col, col));
+        old_expr_parts.push(format!("'{}', OLD.{}", col, col));
+    }
+    let new_expr = format!("json_object({})", new_expr_parts.join(", "));
+    let old_expr = format!("json_object({})", old_expr_parts.join(", "));
+    Ok((new_expr, old_expr))
+}
+
+/// Returns `table`'s column names in schema order, used to detect `ALTER TABLE` changes.
+pub fn get_table_columns(conn: &Connection, table: &str) -> Result<Vec<String>> {
+    let query = format!("PRAGMA table_info({})", table);
+    let mut stmt = conn.prepare(&query)?;
+    let columns = stmt
+        .query_map([], |row| row.get::<_, String>(1))?
+        .collect::<Result<Vec<String>>>()?;
+    Ok(columns)
+}
+
+/// Returns `table`'s primary-key column names, in key order, using `PRAGMA table_info`'s
+/// `pk` flag. Destinations that key on the real primary key need this instead of the
+/// opaque SQLite `rowid`.
+pub fn get_primary_key_columns(conn: &Connection, table: &str) -> Result<Vec<String>> {
+    let query = format!("PRAGMA table_info({})", table);
+    let mut stmt = conn.prepare(&query)?;
+    let mut rows = stmt.query([])?;
+    let mut pk_columns: Vec<(i64, String)> = Vec::new();
+    while let Some(row) = rows.next()? {
+        let pk_order: i64 = row.get(5)?;
+        if pk_order > 0 {
+            let name: String = row.get(1)?;
+            pk_columns.push((pk_order, name));
+        }
+    }
+    pk_columns.sort_by_key(|(order, _)| *order);
+    Ok(pk_columns.into_iter().map(|(_, name)| name).collect())
+}
+
+pub fn drop_triggers_for_table(conn: &Connection, table: &str) -> Result<()> {
+    for action in ["insert", "update", "delete"] {
+        let drop_sql = format!("DROP TRIGGER IF EXISTS change_stream_{}_{};", table, action);
+        conn.execute_batch(&drop_sql)?;
+    }
+    Ok(())
+}
+
+pub fn create_triggers_for_table(conn: &Connection, table: &str) -> Result<()> {
+    let (new_expr, old_expr) = get_json_exprs(conn, table)?;
+
+    let trigger_insert = format!(
+        r#"
+        CREATE TRIGGER IF NOT EXISTS change_stream_{table}_insert
+        AFTER INSERT ON {table}
+        BEGIN
+            INSERT INTO change_stream_log(table_name, action, rowid, new_data)
+            VALUES ('{table}', 'insert', NEW.rowid, {new_expr});
+        END;
+        "#,
+        table = table,
+        new_expr = new_expr
+    );
+    conn.execute_batch(&trigger_insert)?;
+
+    let trigger_update = format!(
+        r#"
+        CREATE TRIGGER IF NOT EXISTS change_stream_{table}_update
+        AFTER UPDATE ON {table}
+        BEGIN
+            INSERT INTO change_stream_log(table_name, action, rowid, new_data, old_data)
+            VALUES ('{table}', 'update', NEW.rowid, {new_expr}, {old_expr});
+        END;
+        "#,
+        table = table,
+        new_expr = new_expr,
+        old_expr = old_expr
+    );
+    conn.execute_batch(&trigger_update)?;
+
+    let trigger_delete = format!(
+        r#"
+        CREATE TRIGGER IF NOT EXISTS change_stream_{table}_delete
+        AFTER DELETE ON {table}
+        BEGIN
+            INSERT INTO change_stream_log(table_name, action, rowid, old_data)
+            VALUES ('{table}', 'delete', OLD.rowid, {old_expr});
+        END;
+        "#,
+        table = table,
+        old_expr = old_expr
+    );
+    conn.execute_batch(&trigger_delete)?;
+
+    Ok(())
+}
+
+pub fn compute_diff(new_data: &str, old_data: &str) -> Value {
+    let new_json: Value = serde_json::from_str(new_data).unwrap_or(Value::Null);
+    let old_json: Value = serde_json::from_str(old_data).unwrap_or(Value::Null);
+
+    let mut diff_map = serde_json::Map::new();
+    if let (Some(new_obj), Some(old_obj)) = (new_json.as_object(), old_json.as_object()) {
+        for (key, new_val) in new_obj {
+            let old_val = old_obj.get(key).unwrap_or(&Value::Null);
+            if new_val != old_val {
+                let diff_entry = json!({
+                    "old": old_val,
+                    "new": new_val
+                });
+                diff_map.insert(key.clone(), diff_entry);
+            }
+        }
+    }
+    Value::Object(diff_map)
+}
+
+/// Tears down the log, cursor and trigger state for `db_path`. The cursor table is
+/// dropped along with the log: `change_stream_log`'s `AUTOINCREMENT` sequence resets to 1
+/// on the next `create_log_table`, so leaving a stale cursor behind would make
+/// `read_new_events`'s `WHERE id > last_id` silently skip every event logged until the
+/// fresh log catches back up to the old high-water mark.
+pub fn cleanup(db_path: &str) -> Result<()> {
+    let conn = Connection::open(db_path)?;
+    conn.execute_batch(
+        "DROP TABLE IF EXISTS change_stream_log;
+         DROP TABLE IF EXISTS change_stream_cursor;",
+    )?;
+
+    let mut stmt = conn.prepare(
+        "SELECT name FROM sqlite_master
+         WHERE type='trigger' AND name LIKE 'change_stream_%';",
+    )?;
+    let trigger_names: Result<Vec<String>> = stmt.query_map([], |row| row.get(0))?.collect();
+    let trigger_names = trigger_names?;
+
+    for trigger in trigger_names {
+        let drop_sql = format!("DROP TRIGGER IF EXISTS {};", trigger);
+        conn.execute_batch(&drop_sql)?;
+    }
+    eprintln!("Cleanup completed");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reproduces the restart sequence a Ctrl-C cleanup triggers: log rows accumulate,
+    /// the cursor advances past some of them, `cleanup()` tears everything down, and the
+    /// tables are recreated as a fresh monitor would on the next run. The post-restart
+    /// cursor must not be left pointing past the id of any row in the fresh log.
+    #[test]
+    fn cleanup_resets_cursor_with_log() {
+        let path = std::env::temp_dir().join(format!(
+            "sqlite_change_stream_cleanup_test_{}.db",
+            std::process::id()
+        ));
+        let path_str = path.to_str().unwrap();
+        let _ = std::fs::remove_file(path_str);
+
+        {
+            let conn = Connection::open(path_str).unwrap();
+            create_log_table(&conn).unwrap();
+            create_cursor_table(&conn).unwrap();
+            for _ in 0..3 {
+                conn.execute(
+                    "INSERT INTO change_stream_log(table_name, action) VALUES ('t', 'insert');",
+                    [],
+                )
+                .unwrap();
+            }
+            save_cursor(&conn, "default", 3).unwrap();
+        }
+
+        cleanup(path_str).unwrap();
+
+        {
+            let conn = Connection::open(path_str).unwrap();
+            create_log_table(&conn).unwrap();
+            create_cursor_table(&conn).unwrap();
+            for _ in 0..2 {
+                conn.execute(
+                    "INSERT INTO change_stream_log(table_name, action) VALUES ('t', 'insert');",
+                    [],
+                )
+                .unwrap();
+            }
+
+            let last_id = load_cursor(&conn, "default").unwrap();
+            let mut stmt = conn
+                .prepare("SELECT id FROM change_stream_log WHERE id > ?1;")
+                .unwrap();
+            let visible: Vec<i64> = stmt
+                .query_map(params![last_id], |row| row.get(0))
+                .unwrap()
+                .collect::<Result<_>>()
+                .unwrap();
+            assert_eq!(
+                visible.len(),
+                2,
+                "expected both post-restart rows to be visible to the consumer, got {:?} with cursor {}",
+                visible,
+                last_id
+            );
+        }
+
+        let _ = std::fs::remove_file(path_str);
+    }
+}
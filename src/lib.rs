@@ -0,0 +1,134 @@
+pub mod config;
+pub mod db;
+pub mod replication;
+pub mod row;
+pub mod schema;
+pub mod sink;
+pub mod stream;
+
+use rusqlite::{Connection, Result};
+use std::time::Duration;
+
+pub use config::Settings;
+pub use schema::SchemaReconciler;
+pub use sink::EventSink;
+pub use stream::{Action, ChangeEvent, ChangeFilter, ChangeStream};
+
+/// Embeddable entry point for the change stream: owns the monitored connection, the
+/// durable consumer cursor and the schema reconciler, so other Rust programs can pull
+/// typed `ChangeEvent`s directly instead of shelling out to the binary and re-parsing
+/// its stdout JSON.
+pub struct ChangeStreamMonitor {
+    conn: Connection,
+    consumer: String,
+    settings: Settings,
+    reconciler: SchemaReconciler,
+    last_id: i64,
+    journal_mode: String,
+}
+
+impl ChangeStreamMonitor {
+    /// Opens `path` with default settings and a `"default"` consumer cursor.
+    pub fn open(path: &str) -> Result<Self> {
+        Self::open_with(path, Settings::default(), "default")
+    }
+
+    /// Opens `path`, applying `settings.journal_mode()` and creating the log and cursor
+    /// tables, resuming `consumer`'s cursor if it already has one.
+    pub fn open_with(path: &str, settings: Settings, consumer: &str) -> Result<Self> {
+        if let Err(err) = settings.validate() {
+            // SQLITE_MISUSE ("library used incorrectly") is the closest fit rusqlite
+            // offers for a caller-supplied configuration error, as opposed to anything
+            // SQLite itself rejected.
+            let code = rusqlite::ffi::Error::new(rusqlite::ffi::SQLITE_MISUSE);
+            return Err(rusqlite::Error::SqliteFailure(code, Some(err)));
+        }
+
+        let conn = Connection::open(path)?;
+        let pragma = format!("PRAGMA journal_mode = {};", settings.journal_mode());
+        let journal_mode: String = conn.query_row(&pragma, [], |row| row.get(0))?;
+
+        db::create_log_table(&conn)?;
+        db::create_cursor_table(&conn)?;
+        let last_id = db::load_cursor(&conn, consumer)?;
+
+        Ok(ChangeStreamMonitor {
+            conn,
+            consumer: consumer.to_string(),
+            settings,
+            reconciler: SchemaReconciler::new(),
+            last_id,
+            journal_mode,
+        })
+    }
+
+    pub fn conn(&self) -> &Connection {
+        &self.conn
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// The journal mode SQLite actually applied, which may differ from what was
+    /// requested (e.g. an in-memory database can't use WAL).
+    pub fn journal_mode(&self) -> &str {
+        &self.journal_mode
+    }
+
+    /// Creates triggers for every currently-known user table (after applying the
+    /// include/exclude list) and primes the schema reconciler against them.
+    pub fn install_triggers(&mut self) -> Result<()> {
+        for table in self.settings.filter_tables(db::get_user_tables(&self.conn)?) {
+            db::create_triggers_for_table(&self.conn, &table)?;
+        }
+        self.reconciler.prime(&self.conn, &self.settings)
+    }
+
+    /// Re-syncs triggers against any schema changes since the last call, then returns
+    /// every change since the durable cursor, advancing it past the returned events.
+    pub fn next_batch(&mut self) -> Result<Vec<ChangeEvent>> {
+        self.reconciler.reconcile(&self.conn, &self.settings)?;
+        let events = stream::read_new_events(&self.conn, self.last_id, self.settings.capture_mode)?;
+        if let Some(last) = events.last() {
+            db::save_cursor(&self.conn, &self.consumer, last.id)?;
+            self.last_id = last.id;
+        }
+        Ok(events)
+    }
+
+    /// Runs `next_batch` forever, delivering each non-empty batch to `sinks` before
+    /// advancing the cursor (at-least-once delivery) and publishing every event onto
+    /// `change_stream`, with periodic `change_stream_log` pruning.
+    pub async fn run_forever(
+        &mut self,
+        change_stream: &ChangeStream,
+        sinks: &[Box<dyn EventSink>],
+    ) -> Result<()> {
+        let mut cycles_since_prune: u32 = 0;
+        loop {
+            self.reconciler.reconcile(&self.conn, &self.settings)?;
+
+            let events = stream::read_new_events(&self.conn, self.last_id, self.settings.capture_mode)?;
+            if !events.is_empty() {
+                stream::deliver_with_retry(sinks, &events).await;
+
+                let new_last_id = events.last().map(|event| event.id).unwrap_or(self.last_id);
+                db::save_cursor(&self.conn, &self.consumer, new_last_id)?;
+                self.last_id = new_last_id;
+
+                for event in events {
+                    change_stream.publish(event);
+                }
+            }
+
+            cycles_since_prune += 1;
+            if cycles_since_prune >= stream::PRUNE_INTERVAL_CYCLES {
+                db::prune_log(&self.conn)?;
+                cycles_since_prune = 0;
+            }
+
+            tokio::time::sleep(Duration::from_millis(self.settings.poll_interval_ms)).await;
+        }
+    }
+}
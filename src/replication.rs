@@ -0,0 +1,153 @@
+use serde_json::{json, Map, Value};
+use tokio::sync::broadcast;
+
+use crate::stream::{Action, ChangeEvent, ChangeFilter, ChangeStream};
+
+/// Subscribes to every event and prints Fivetran-style replication records instead of
+/// the default `ChangeEvent` JSON: upserts carry `_cs_synced_at`, and deletes keep the
+/// full row payload with `_cs_deleted = true` rather than dropping it, so a destination
+/// can tombstone instead of hard-deleting. With `history_mode`, updates emit both the
+/// pre-image and post-image as separate records keyed by `(rowid, ts)` so a consumer can
+/// reconstruct the full change timeline of a row.
+pub fn spawn_printer(stream: &ChangeStream, history_mode: bool) {
+    let mut rx = stream.subscribe(ChangeFilter::default());
+    tokio::spawn(async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => {
+                    for record in build_records(&event, history_mode) {
+                        println!("{}", record);
+                    }
+                }
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+fn build_records(event: &ChangeEvent, history_mode: bool) -> Vec<Value> {
+    match event.action {
+        Action::Delete => vec![tombstone_record(event)],
+        Action::Update if history_mode => vec![
+            versioned_record(event, event.old_data.as_deref(), "old"),
+            versioned_record(event, event.new_data.as_deref(), "new"),
+        ],
+        Action::Insert | Action::Update => vec![upsert_record(event)],
+        Action::SchemaChange => vec![],
+    }
+}
+
+fn row_with_pk(event: &ChangeEvent, data: Option<&str>) -> Map<String, Value> {
+    let mut row = data
+        .and_then(|raw| serde_json::from_str::<Value>(raw).ok())
+        .and_then(|value| value.as_object().cloned())
+        .unwrap_or_default();
+    if let Some(pk) = &event.pk {
+        for (column, value) in pk {
+            row.insert(column.clone(), value.clone());
+        }
+    }
+    row
+}
+
+fn upsert_record(event: &ChangeEvent) -> Value {
+    let mut row = row_with_pk(event, event.new_data.as_deref());
+    row.insert("_cs_synced_at".to_string(), json!(event.ts));
+    Value::Object(row)
+}
+
+fn tombstone_record(event: &ChangeEvent) -> Value {
+    let mut row = row_with_pk(event, event.old_data.as_deref());
+    row.insert("_cs_synced_at".to_string(), json!(event.ts));
+    row.insert("_cs_deleted".to_string(), json!(true));
+    Value::Object(row)
+}
+
+fn versioned_record(event: &ChangeEvent, data: Option<&str>, version: &str) -> Value {
+    let mut row = row_with_pk(event, data);
+    row.insert("_cs_synced_at".to_string(), json!(event.ts));
+    row.insert("_cs_version".to_string(), json!(version));
+    row.insert("_cs_rowid".to_string(), json!(event.rowid));
+    Value::Object(row)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(action: Action, pk: Option<Map<String, Value>>) -> ChangeEvent {
+        ChangeEvent {
+            id: 1,
+            table: "widgets".to_string(),
+            action,
+            rowid: Some(42),
+            pk,
+            new_data: Some(r#"{"id":1,"name":"new"}"#.to_string()),
+            old_data: Some(r#"{"id":1,"name":"old"}"#.to_string()),
+            changed_fields: None,
+            ts: "2026-07-30T00:00:00Z".to_string(),
+        }
+    }
+
+    fn pk(value: i64) -> Map<String, Value> {
+        let mut pk = Map::new();
+        pk.insert("id".to_string(), json!(value));
+        pk
+    }
+
+    #[test]
+    fn insert_produces_single_upsert_from_new_data() {
+        let records = build_records(&event(Action::Insert, None), false);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["name"], json!("new"));
+        assert_eq!(records[0]["_cs_synced_at"], json!("2026-07-30T00:00:00Z"));
+    }
+
+    #[test]
+    fn insert_without_declared_pk_omits_pk_override() {
+        let records = build_records(&event(Action::Insert, None), false);
+        // The row's own "id" field (from new_data) still comes through even with no
+        // separately-tracked pk, since row_with_pk only overlays `event.pk` when present.
+        assert_eq!(records[0]["id"], json!(1));
+    }
+
+    #[test]
+    fn insert_with_declared_pk_merges_pk_into_row() {
+        let records = build_records(&event(Action::Insert, Some(pk(7))), false);
+        assert_eq!(records[0]["id"], json!(7));
+    }
+
+    #[test]
+    fn update_without_history_mode_produces_single_post_image_upsert() {
+        let records = build_records(&event(Action::Update, None), false);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["name"], json!("new"));
+        assert!(records[0].get("_cs_version").is_none());
+    }
+
+    #[test]
+    fn update_with_history_mode_produces_pre_and_post_image_records() {
+        let records = build_records(&event(Action::Update, None), true);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["_cs_version"], json!("old"));
+        assert_eq!(records[0]["name"], json!("old"));
+        assert_eq!(records[0]["_cs_rowid"], json!(42));
+        assert_eq!(records[1]["_cs_version"], json!("new"));
+        assert_eq!(records[1]["name"], json!("new"));
+    }
+
+    #[test]
+    fn delete_produces_tombstone_from_old_data() {
+        let records = build_records(&event(Action::Delete, None), false);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["name"], json!("old"));
+        assert_eq!(records[0]["_cs_deleted"], json!(true));
+    }
+
+    #[test]
+    fn schema_change_produces_no_records() {
+        let records = build_records(&event(Action::SchemaChange, None), false);
+        assert!(records.is_empty());
+    }
+}
@@ -0,0 +1,33 @@
+use rusqlite::{Result, Row};
+
+/// Extracts a typed value from a `rusqlite::Row`, so row-to-struct mapping lives in one
+/// place instead of being destructured ad hoc at each call site.
+pub trait FromRow: Sized {
+    fn from_row(row: &Row<'_>) -> Result<Self>;
+}
+
+/// The raw `change_stream_log` columns, before they're reshaped into a `ChangeEvent`
+/// (primary-key extraction, `capture_mode` shaping).
+pub struct RawLogRow {
+    pub id: i64,
+    pub table_name: String,
+    pub action: String,
+    pub rowid: Option<i64>,
+    pub new_data: Option<String>,
+    pub old_data: Option<String>,
+    pub ts: String,
+}
+
+impl FromRow for RawLogRow {
+    fn from_row(row: &Row<'_>) -> Result<Self> {
+        Ok(RawLogRow {
+            id: row.get(0)?,
+            table_name: row.get(1)?,
+            action: row.get(2)?,
+            rowid: row.get(3)?,
+            new_data: row.get(4)?,
+            old_data: row.get(5)?,
+            ts: row.get(6)?,
+        })
+    }
+}
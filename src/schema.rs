@@ -0,0 +1,69 @@
+use rusqlite::{params, Connection, Result};
+use serde_json::json;
+use std::collections::HashMap;
+
+use crate::config::Settings;
+use crate::db::{
+    create_triggers_for_table, drop_triggers_for_table, get_table_columns, get_user_tables,
+};
+
+/// Watches `sqlite_master` for tables that are new or whose column set changed since the
+/// last reconciliation, so triggers created at startup don't silently go stale after an
+/// `ALTER TABLE` or `CREATE TABLE`.
+pub struct SchemaReconciler {
+    signatures: HashMap<String, String>,
+}
+
+impl Default for SchemaReconciler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SchemaReconciler {
+    pub fn new() -> Self {
+        SchemaReconciler {
+            signatures: HashMap::new(),
+        }
+    }
+
+    /// Seeds the reconciler with the column signatures of tables whose triggers were
+    /// already built at startup, so they aren't treated as changed on the first poll.
+    pub fn prime(&mut self, conn: &Connection, settings: &Settings) -> Result<()> {
+        for table in settings.filter_tables(get_user_tables(conn)?) {
+            let signature = column_signature(conn, &table)?;
+            self.signatures.insert(table, signature);
+        }
+        Ok(())
+    }
+
+    /// Re-scans user tables and recreates triggers for anything new or changed,
+    /// recording a `schema_change` log row for each so downstream consumers notice.
+    pub fn reconcile(&mut self, conn: &Connection, settings: &Settings) -> Result<()> {
+        for table in settings.filter_tables(get_user_tables(conn)?) {
+            let signature = column_signature(conn, &table)?;
+            if self.signatures.get(&table) == Some(&signature) {
+                continue;
+            }
+            drop_triggers_for_table(conn, &table)?;
+            create_triggers_for_table(conn, &table)?;
+            record_schema_change(conn, &table)?;
+            self.signatures.insert(table, signature);
+        }
+        Ok(())
+    }
+}
+
+fn column_signature(conn: &Connection, table: &str) -> Result<String> {
+    Ok(get_table_columns(conn, table)?.join(","))
+}
+
+fn record_schema_change(conn: &Connection, table: &str) -> Result<()> {
+    let columns = get_table_columns(conn, table)?;
+    let new_data = json!({ "columns": columns }).to_string();
+    conn.execute(
+        "INSERT INTO change_stream_log(table_name, action, new_data) VALUES (?1, 'schema_change', ?2);",
+        params![table, new_data],
+    )?;
+    Ok(())
+}
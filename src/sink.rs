@@ -0,0 +1,75 @@
+use async_trait::async_trait;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::fs::OpenOptions;
+use tokio::io::AsyncWriteExt;
+
+use crate::stream::{to_json, ChangeEvent};
+
+/// A hung webhook endpoint must surface as a retryable `Err` within bounded time rather
+/// than blocking the single poll loop's `deliver_with_retry` call forever.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// An external destination for delivered batches of change events. Implementations
+/// should be idempotent-friendly: a batch may be redelivered if a previous `deliver`
+/// call succeeded but the caller couldn't confirm it (at-least-once, not exactly-once).
+#[async_trait]
+pub trait EventSink: Send + Sync {
+    async fn deliver(&self, batch: &[ChangeEvent]) -> anyhow::Result<()>;
+}
+
+/// Appends each event as one JSON line to a file.
+pub struct NdjsonSink {
+    path: PathBuf,
+}
+
+impl NdjsonSink {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        NdjsonSink { path: path.into() }
+    }
+}
+
+#[async_trait]
+impl EventSink for NdjsonSink {
+    async fn deliver(&self, batch: &[ChangeEvent]) -> anyhow::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        for event in batch {
+            file.write_all(to_json(event).to_string().as_bytes()).await?;
+            file.write_all(b"\n").await?;
+        }
+        Ok(())
+    }
+}
+
+/// Posts each batch as a single JSON array to an HTTP endpoint.
+pub struct WebhookSink {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl WebhookSink {
+    pub fn new(url: impl Into<String>) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(WEBHOOK_TIMEOUT)
+            .build()
+            .expect("reqwest client with a fixed timeout is infallible to build");
+        WebhookSink {
+            url: url.into(),
+            client,
+        }
+    }
+}
+
+#[async_trait]
+impl EventSink for WebhookSink {
+    async fn deliver(&self, batch: &[ChangeEvent]) -> anyhow::Result<()> {
+        let payload: Vec<_> = batch.iter().map(to_json).collect();
+        let response = self.client.post(&self.url).json(&payload).send().await?;
+        response.error_for_status()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,322 @@
+use rusqlite::{params, Connection, Result};
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::config::CaptureMode;
+use crate::db::compute_diff;
+use crate::row::{FromRow, RawLogRow};
+use crate::sink::EventSink;
+
+const CHANNEL_CAPACITY: usize = 1024;
+/// Run log retention once every this many poll cycles (roughly once a minute at the
+/// default 1s poll interval).
+pub(crate) const PRUNE_INTERVAL_CYCLES: u32 = 60;
+const INITIAL_DELIVERY_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_DELIVERY_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Action {
+    Insert,
+    Update,
+    Delete,
+    /// Synthetic action emitted by the schema reconciler when a table's columns change.
+    SchemaChange,
+}
+
+impl Action {
+    fn from_db_str(action: &str) -> Option<Action> {
+        match action {
+            "insert" => Some(Action::Insert),
+            "update" => Some(Action::Update),
+            "delete" => Some(Action::Delete),
+            "schema_change" => Some(Action::SchemaChange),
+            _ => None,
+        }
+    }
+
+    fn as_db_str(self) -> &'static str {
+        match self {
+            Action::Insert => "insert",
+            Action::Update => "update",
+            Action::Delete => "delete",
+            Action::SchemaChange => "schema_change",
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct ChangeEvent {
+    pub id: i64,
+    pub table: String,
+    pub action: Action,
+    pub rowid: Option<i64>,
+    /// Primary-key column values, when `table` declares one, for destinations that key
+    /// on the real primary key rather than the opaque `rowid`.
+    pub pk: Option<serde_json::Map<String, serde_json::Value>>,
+    pub new_data: Option<String>,
+    pub old_data: Option<String>,
+    pub changed_fields: Option<serde_json::Value>,
+    pub ts: String,
+}
+
+/// Per-subscriber filter: `None` on either field means match-all for that dimension.
+#[derive(Clone, Debug, Default)]
+pub struct ChangeFilter {
+    pub tables: Option<HashSet<String>>,
+    pub actions: Option<HashSet<Action>>,
+}
+
+impl ChangeFilter {
+    fn matches(&self, event: &ChangeEvent) -> bool {
+        let table_ok = self
+            .tables
+            .as_ref()
+            .is_none_or(|tables| tables.contains(&event.table));
+        let action_ok = self
+            .actions
+            .as_ref()
+            .is_none_or(|actions| actions.contains(&event.action));
+        table_ok && action_ok
+    }
+}
+
+/// Fans a single internal event feed out to any number of filtered subscribers.
+pub struct ChangeStream {
+    sender: broadcast::Sender<ChangeEvent>,
+}
+
+impl Default for ChangeStream {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChangeStream {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        ChangeStream { sender }
+    }
+
+    /// Returns a receiver that only ever yields events matching `filter`. A background
+    /// task forwards matching events from the shared broadcast channel for the lifetime
+    /// of the returned receiver.
+    pub fn subscribe(&self, filter: ChangeFilter) -> broadcast::Receiver<ChangeEvent> {
+        let (tx, rx) = broadcast::channel(CHANNEL_CAPACITY);
+        let mut upstream = self.sender.subscribe();
+        tokio::spawn(async move {
+            loop {
+                match upstream.recv().await {
+                    Ok(event) => {
+                        if filter.matches(&event) && tx.send(event).is_err() {
+                            break;
+                        }
+                    }
+                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+                // Checked on every upstream event, not just ones this filter forwards,
+                // so a narrow filter can't keep this task alive after `rx` is dropped.
+                if tx.receiver_count() == 0 {
+                    break;
+                }
+            }
+        });
+        rx
+    }
+
+    pub(crate) fn publish(&self, event: ChangeEvent) {
+        // No active receivers is not an error; the event is simply dropped.
+        let _ = self.sender.send(event);
+    }
+}
+
+/// Subscribes to every event and prints it as a JSON line, matching the tool's
+/// previous stdout-only behavior.
+pub fn spawn_stdout_printer(stream: &ChangeStream) {
+    let mut rx = stream.subscribe(ChangeFilter::default());
+    tokio::spawn(async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => print_event(&event),
+                Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+fn print_event(event: &ChangeEvent) {
+    println!("{}", to_json(event));
+}
+
+/// Renders a `ChangeEvent` as the JSON shape emitted to stdout and sinks alike.
+pub fn to_json(event: &ChangeEvent) -> serde_json::Value {
+    let action_str = event.action.as_db_str();
+    if let Some(changed_fields) = &event.changed_fields {
+        json!({
+            "id": event.id,
+            "table": event.table,
+            "action": action_str,
+            "rowid": event.rowid,
+            "pk": event.pk,
+            "changed_fields": changed_fields,
+            "timestamp": event.ts,
+        })
+    } else {
+        json!({
+            "id": event.id,
+            "table": event.table,
+            "action": action_str,
+            "rowid": event.rowid,
+            "pk": event.pk,
+            "new_data": event.new_data,
+            "old_data": event.old_data,
+            "timestamp": event.ts,
+        })
+    }
+}
+
+/// Reads new `change_stream_log` rows once and converts each into a typed `ChangeEvent`,
+/// shaping `update` payloads per `capture_mode`.
+pub(crate) fn read_new_events(
+    conn: &Connection,
+    last_id: i64,
+    capture_mode: CaptureMode,
+) -> Result<Vec<ChangeEvent>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, table_name, action, rowid, new_data, old_data, ts
+         FROM change_stream_log
+         WHERE id > ?1
+         ORDER BY id ASC;",
+    )?;
+    let rows = stmt.query_map(params![last_id], RawLogRow::from_row)?;
+
+    let mut pk_columns_by_table: HashMap<String, Vec<String>> = HashMap::new();
+    let mut events = Vec::new();
+    for row in rows {
+        let RawLogRow {
+            id,
+            table_name,
+            action: action_str,
+            rowid,
+            new_data,
+            old_data,
+            ts,
+        } = row?;
+        let action = match Action::from_db_str(&action_str) {
+            Some(action) => action,
+            None => continue,
+        };
+
+        let pk_columns = match pk_columns_by_table.get(&table_name) {
+            Some(columns) => columns.clone(),
+            None => {
+                let columns = crate::db::get_primary_key_columns(conn, &table_name)?;
+                pk_columns_by_table.insert(table_name.clone(), columns.clone());
+                columns
+            }
+        };
+        let pk = extract_pk(&pk_columns, new_data.as_deref().or(old_data.as_deref()));
+
+        let (new_data, old_data, changed_fields) =
+            if action == Action::Update && capture_mode == CaptureMode::DiffOnly {
+                let changed_fields = match (new_data.as_ref(), old_data.as_ref()) {
+                    (Some(new_str), Some(old_str)) => Some(compute_diff(new_str, old_str)),
+                    _ => None,
+                };
+                (None, None, changed_fields)
+            } else {
+                (new_data, old_data, None)
+            };
+        events.push(ChangeEvent {
+            id,
+            table: table_name,
+            action,
+            rowid,
+            pk,
+            new_data,
+            old_data,
+            changed_fields,
+            ts,
+        });
+    }
+    Ok(events)
+}
+
+/// Pulls the primary-key column values out of a row's JSON payload.
+fn extract_pk(pk_columns: &[String], data: Option<&str>) -> Option<serde_json::Map<String, serde_json::Value>> {
+    if pk_columns.is_empty() {
+        return None;
+    }
+    let row: serde_json::Value = serde_json::from_str(data?).ok()?;
+    let row = row.as_object()?;
+    let mut pk = serde_json::Map::new();
+    for column in pk_columns {
+        if let Some(value) = row.get(column) {
+            pk.insert(column.clone(), value.clone());
+        }
+    }
+    Some(pk)
+}
+
+/// Delivers `batch` to every sink, retrying the whole batch with exponential backoff
+/// (capped at `MAX_DELIVERY_BACKOFF`) until all sinks succeed. Never gives up: the
+/// consumer cursor must not advance past undelivered events.
+pub(crate) async fn deliver_with_retry(sinks: &[Box<dyn EventSink>], batch: &[ChangeEvent]) {
+    if sinks.is_empty() || batch.is_empty() {
+        return;
+    }
+    let mut backoff = INITIAL_DELIVERY_BACKOFF;
+    loop {
+        let mut failure = None;
+        for sink in sinks {
+            if let Err(err) = sink.deliver(batch).await {
+                failure = Some(err);
+                break;
+            }
+        }
+        match failure {
+            None => return,
+            Some(err) => {
+                eprintln!("Sink delivery failed: {:?}; retrying in {:?}", err, backoff);
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_DELIVERY_BACKOFF);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extract_pk_returns_none_without_declared_pk_columns() {
+        assert_eq!(extract_pk(&[], Some(r#"{"id":1}"#)), None);
+    }
+
+    #[test]
+    fn extract_pk_returns_none_without_data() {
+        assert_eq!(extract_pk(&["id".to_string()], None), None);
+    }
+
+    #[test]
+    fn extract_pk_pulls_only_the_declared_columns() {
+        let pk = extract_pk(&["id".to_string()], Some(r#"{"id":1,"name":"a"}"#)).unwrap();
+        assert_eq!(pk.len(), 1);
+        assert_eq!(pk.get("id"), Some(&serde_json::json!(1)));
+    }
+
+    #[test]
+    fn extract_pk_supports_composite_keys() {
+        let pk = extract_pk(
+            &["tenant".to_string(), "id".to_string()],
+            Some(r#"{"tenant":"acme","id":1,"name":"a"}"#),
+        )
+        .unwrap();
+        assert_eq!(pk.get("tenant"), Some(&serde_json::json!("acme")));
+        assert_eq!(pk.get("id"), Some(&serde_json::json!(1)));
+    }
+}